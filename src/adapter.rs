@@ -1,6 +1,10 @@
 use {JsonObject, Request, Reply, Method, ErrorKind, Error};
 use futures::{BoxFuture, Future};
+use futures::future::{loop_fn, Loop, ok, err};
 use serde_json::Value as JsonValue;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_timer::Timer;
 
 /**
 Converts a Request to a static Reply from a database.
@@ -27,23 +31,150 @@ pub trait Adapter: Send + Sync {
   call.
   */
   fn handle(&self, req: Request) -> BoxFuture<Reply, Error> {
+    let req_snapshot = req.to_req();
     let res = match (req.method().clone(), req.id().clone()) {
       (Method::List, _) => self.list(req.params()),
       (Method::Post, _) => self.post(req.data(), req.params()),
       (Method::Get, Some(ref id)) => self.get(id, req.params()),
       (Method::Delete, Some(ref id)) => self.delete(id, req.params()),
       (Method::Patch, Some(ref id)) => self.patch(id, req.data(), req.params()),
-      (_, None) => return Error::bad_request("missing id in request"),
-      (Method::Listen, _) => return Error::server_error("passed listen request to database adapter"),
-      (Method::Action(_), _) => return Error::server_error("passed action request to database adapter"),
+      (_, None) => return err(Error::bad_request("missing id in request").with_req(req_snapshot)).boxed(),
+      (Method::Listen, _) => return err(Error::server_error("passed listen request to database adapter").with_req(req_snapshot)).boxed(),
+      (Method::Action(_), _) => return err(Error::server_error("passed action request to database adapter").with_req(req_snapshot)).boxed(),
     };
     res.then(move |res| match res {
       Ok(val) => Ok(req.into_reply(val)),
-      Err((kind, val)) => Err(Error::new(kind, val)),
+      Err((kind, val)) => Err(Error::new(kind, val).with_req(req_snapshot)),
     }).boxed()
   }
 }
 
+/**
+Configuration for `RetryAdapter`'s exponential backoff.
+
+`retry_if` decides whether a given `ErrorKind` is worth retrying at all; the default treats
+`ErrorKind::ServerError` as transient and everything else (bad request, not found, ...) as
+terminal. `retry_patch` additionally gates whether `patch` calls are retried, since patches
+are only safe to retry when callers know them to be idempotent in their own schema.
+*/
+#[derive(Clone)]
+pub struct RetryConfig {
+  pub max_attempts: u32,
+  pub base_delay: Duration,
+  pub max_delay: Duration,
+  pub retry_patch: bool,
+  pub retry_if: fn(&ErrorKind) -> bool,
+}
+
+impl Default for RetryConfig {
+  fn default() -> RetryConfig {
+    RetryConfig {
+      max_attempts: 3,
+      base_delay: Duration::from_millis(100),
+      max_delay: Duration::from_secs(5),
+      retry_patch: false,
+      retry_if: default_retry_if,
+    }
+  }
+}
+
+fn default_retry_if(kind: &ErrorKind) -> bool {
+  match *kind {
+    ErrorKind::ServerError => true,
+    _ => false,
+  }
+}
+
+fn backoff_delay(config: &RetryConfig, attempt: u32) -> Duration {
+  let multiplier = 1u32.checked_shl(attempt.saturating_sub(1)).unwrap_or(u32::max_value());
+  match config.base_delay.checked_mul(multiplier) {
+    Some(delay) if delay < config.max_delay => delay,
+    _ => config.max_delay,
+  }
+}
+
+/// Runs `attempt` once, and again (with backoff) for as long as it keeps failing with a
+/// retryable error and `retryable` is true, up to `config.max_attempts`.
+fn with_retries<F>(attempt: F, config: RetryConfig, retryable: bool) -> BoxFuture<JsonObject, (ErrorKind, JsonValue)>
+  where F: Fn() -> BoxFuture<JsonObject, (ErrorKind, JsonValue)> + Send + Sync + 'static
+{
+  if !retryable {
+    return attempt();
+  }
+  loop_fn(1u32, move |attempt_num| {
+    let config = config.clone();
+    attempt().then(move |res| -> BoxFuture<Loop<JsonObject, u32>, (ErrorKind, JsonValue)> {
+      match res {
+        Ok(val) => ok(Loop::Break(val)).boxed(),
+        Err((kind, val)) => {
+          if attempt_num >= config.max_attempts || !(config.retry_if)(&kind) {
+            return ::futures::future::err((kind, val)).boxed();
+          }
+          Timer::default().sleep(backoff_delay(&config, attempt_num))
+            .then(move |_| ok(Loop::Continue(attempt_num + 1)))
+            .boxed()
+        },
+      }
+    })
+  }).boxed()
+}
+
+/**
+Wraps any `Adapter` so that transient failures (a flaky database connection, a momentary
+timeout) are retried with exponential backoff instead of being surfaced straight to the
+caller. Only idempotent methods are retried — `list`, `get`, `delete`, and `patch` when
+`RetryConfig::retry_patch` is set — `post` is always passed straight through.
+*/
+pub struct RetryAdapter<A: Adapter> {
+  inner: Arc<A>,
+  config: RetryConfig,
+}
+
+impl<A: Adapter> RetryAdapter<A> {
+  pub fn new(inner: A, config: RetryConfig) -> RetryAdapter<A> {
+    RetryAdapter {
+      inner: Arc::new(inner),
+      config: config,
+    }
+  }
+}
+
+impl<A: Adapter + 'static> Adapter for RetryAdapter<A> {
+  fn list(&self, params: &JsonObject) -> BoxFuture<JsonObject, (ErrorKind, JsonValue)> {
+    let inner = self.inner.clone();
+    let params = params.clone();
+    with_retries(move || inner.list(&params), self.config.clone(), true)
+  }
+
+  fn get(&self, id: &str, params: &JsonObject) -> BoxFuture<JsonObject, (ErrorKind, JsonValue)> {
+    let inner = self.inner.clone();
+    let id = id.to_string();
+    let params = params.clone();
+    with_retries(move || inner.get(&id, &params), self.config.clone(), true)
+  }
+
+  fn post(&self, data: &JsonObject, params: &JsonObject) -> BoxFuture<JsonObject, (ErrorKind, JsonValue)> {
+    // never retried: POST isn't idempotent, so replaying it on a timeout could double-create.
+    self.inner.post(data, params)
+  }
+
+  fn patch(&self, id: &str, data: &JsonObject, params: &JsonObject) -> BoxFuture<JsonObject, (ErrorKind, JsonValue)> {
+    let inner = self.inner.clone();
+    let id = id.to_string();
+    let data = data.clone();
+    let params = params.clone();
+    let retryable = self.config.retry_patch;
+    with_retries(move || inner.patch(&id, &data, &params), self.config.clone(), retryable)
+  }
+
+  fn delete(&self, id: &str, params: &JsonObject) -> BoxFuture<JsonObject, (ErrorKind, JsonValue)> {
+    let inner = self.inner.clone();
+    let id = id.to_string();
+    let params = params.clone();
+    with_retries(move || inner.delete(&id, &params), self.config.clone(), true)
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -121,4 +252,80 @@ mod tests {
     let adapter = TestAdapter{};
     let _res = adapter.handle(make_req(Method::Post, None)).wait().unwrap_err();
   }
+
+  use std::sync::atomic::{AtomicUsize, Ordering};
+
+  // Fails `fails_until` times with a `ServerError` before succeeding, so retry behavior can
+  // be observed by counting attempts.
+  struct FlakyAdapter {
+    attempts: AtomicUsize,
+    fails_until: usize,
+  }
+
+  impl Adapter for FlakyAdapter {
+    fn list(&self, _params: &JsonObject) -> BoxFuture<JsonObject, (ErrorKind, JsonValue)> {
+      let attempt = self.attempts.fetch_add(1, Ordering::SeqCst) + 1;
+      if attempt <= self.fails_until {
+        err((ErrorKind::ServerError, json!({"error": "flaky"}))).boxed()
+      } else {
+        ok(JsonObject::new()).boxed()
+      }
+    }
+    fn get(&self, _id: &str, _params: &JsonObject) -> BoxFuture<JsonObject, (ErrorKind, JsonValue)> {
+      ok(JsonObject::new()).boxed()
+    }
+    fn post(&self, _data: &JsonObject, _params: &JsonObject) -> BoxFuture<JsonObject, (ErrorKind, JsonValue)> {
+      self.attempts.fetch_add(1, Ordering::SeqCst);
+      err((ErrorKind::ServerError, json!({"error": "flaky"}))).boxed()
+    }
+    fn patch(&self, _id: &str, _data: &JsonObject, _params: &JsonObject) -> BoxFuture<JsonObject, (ErrorKind, JsonValue)> {
+      self.attempts.fetch_add(1, Ordering::SeqCst);
+      err((ErrorKind::ServerError, json!({"error": "flaky"}))).boxed()
+    }
+    fn delete(&self, _id: &str, _params: &JsonObject) -> BoxFuture<JsonObject, (ErrorKind, JsonValue)> {
+      ok(JsonObject::new()).boxed()
+    }
+  }
+
+  fn fast_retry_config() -> RetryConfig {
+    RetryConfig {
+      max_attempts: 5,
+      base_delay: Duration::from_millis(1),
+      max_delay: Duration::from_millis(5),
+      ..RetryConfig::default()
+    }
+  }
+
+  #[test]
+  fn retry_adapter_retries_transient_errors() {
+    let inner = FlakyAdapter { attempts: AtomicUsize::new(0), fails_until: 2 };
+    let adapter = RetryAdapter::new(inner, fast_retry_config());
+    let res = adapter.handle(make_req(Method::List, None)).wait().unwrap();
+    assert!(res.data().is_some());
+    assert_eq!(adapter.inner.attempts.load(Ordering::SeqCst), 3);
+  }
+
+  #[test]
+  fn retry_adapter_gives_up_after_max_attempts() {
+    let inner = FlakyAdapter { attempts: AtomicUsize::new(0), fails_until: 100 };
+    let adapter = RetryAdapter::new(inner, fast_retry_config());
+    let _res = adapter.handle(make_req(Method::List, None)).wait().unwrap_err();
+    assert_eq!(adapter.inner.attempts.load(Ordering::SeqCst), 5);
+  }
+
+  #[test]
+  fn retry_adapter_never_retries_post() {
+    let inner = FlakyAdapter { attempts: AtomicUsize::new(0), fails_until: 100 };
+    let adapter = RetryAdapter::new(inner, fast_retry_config());
+    let _res = adapter.handle(make_req(Method::Post, None)).wait().unwrap_err();
+    assert_eq!(adapter.inner.attempts.load(Ordering::SeqCst), 1);
+  }
+
+  #[test]
+  fn retry_adapter_skips_patch_unless_configured() {
+    let inner = FlakyAdapter { attempts: AtomicUsize::new(0), fails_until: 100 };
+    let adapter = RetryAdapter::new(inner, fast_retry_config());
+    let _res = adapter.handle(make_req(Method::Patch, Some("12"))).wait().unwrap_err();
+    assert_eq!(adapter.inner.attempts.load(Ordering::SeqCst), 1);
+  }
 }
@@ -0,0 +1,181 @@
+use {JsonValue, Reply, Req};
+use std::error::Error as StdError;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+  BadRequest,
+  NotFound,
+  ServerError,
+}
+
+impl ErrorKind {
+  /// The HTTP status code errors of this kind should be reported with.
+  pub fn http_status(&self) -> u16 {
+    match *self {
+      ErrorKind::BadRequest => 400,
+      ErrorKind::NotFound => 404,
+      ErrorKind::ServerError => 500,
+    }
+  }
+}
+
+/**
+The one error type everything above the adapter layer deals with: an `ErrorKind` (so it
+always maps to a real HTTP status via `ErrorKind::http_status`), a structured JSON payload
+describing what went wrong, and optionally the lower-level error that caused it.
+
+Adapters themselves only ever return the looser `(ErrorKind, JsonValue)` tuple — that's all
+a database adapter should need to produce. `Adapter::handle`/`Resource::handle` lift that
+into a proper `Error`, attaching whatever `Req` was in flight so `into_reply` can still pick
+the right content type for the response.
+*/
+pub struct Error {
+  kind: ErrorKind,
+  payload: JsonValue,
+  source: Option<Box<StdError + Send + Sync>>,
+  req: Option<Req>,
+}
+
+impl Error {
+  pub fn new(kind: ErrorKind, payload: JsonValue) -> Error {
+    Error {
+      kind: kind,
+      payload: payload,
+      source: None,
+      req: None,
+    }
+  }
+
+  /// Attaches the lower-level error that caused this one, available afterwards through
+  /// `StdError::cause`.
+  pub fn with_source<E: StdError + Send + Sync + 'static>(mut self, source: E) -> Error {
+    self.source = Some(Box::new(source));
+    self
+  }
+
+  /// Attaches a snapshot of the request that triggered this error (typically via
+  /// `Request::to_req`), so `into_reply` can still negotiate content type from its
+  /// `Accept` header the same way a successful `Reply` would.
+  pub fn with_req(mut self, req: Req) -> Error {
+    self.req = Some(req);
+    self
+  }
+
+  pub fn bad_request<T: Into<JsonValue>>(payload: T) -> Error {
+    Error::new(ErrorKind::BadRequest, payload.into())
+  }
+
+  pub fn not_found<T: Into<JsonValue>>(payload: T) -> Error {
+    Error::new(ErrorKind::NotFound, payload.into())
+  }
+
+  pub fn server_error<T: Into<JsonValue>>(payload: T) -> Error {
+    Error::new(ErrorKind::ServerError, payload.into())
+  }
+
+  pub fn kind(&self) -> &ErrorKind {
+    &self.kind
+  }
+
+  pub fn payload(&self) -> &JsonValue {
+    &self.payload
+  }
+
+  pub fn is_bad_request(&self) -> bool {
+    self.kind == ErrorKind::BadRequest
+  }
+
+  pub fn is_not_found(&self) -> bool {
+    self.kind == ErrorKind::NotFound
+  }
+
+  pub fn is_server_error(&self) -> bool {
+    self.kind == ErrorKind::ServerError
+  }
+
+  /// Renders the `{ "error": { "code", "message" } }` envelope `Reply::to_http` sends for
+  /// errors, with the HTTP status set from `ErrorKind::http_status()`. Carries along
+  /// whatever `Req` was attached via `with_req`, so the error body gets the same content
+  /// negotiation a successful reply would.
+  pub fn into_reply(self) -> Reply {
+    let status = self.kind.http_status();
+    let body = json!({
+      "error": {
+        "code": status,
+        "message": self.payload,
+      }
+    });
+    Reply::new(status as i64, self.req, body)
+  }
+}
+
+impl fmt::Debug for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("Error")
+      .field("kind", &self.kind)
+      .field("payload", &self.payload)
+      .finish()
+  }
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{:?}: {}", self.kind, self.payload)
+  }
+}
+
+impl StdError for Error {
+  fn description(&self) -> &str {
+    "backtalk error"
+  }
+
+  fn cause(&self) -> Option<&StdError> {
+    self.source.as_ref().map(|e| &**e as &StdError)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use Method;
+  use hyper::StatusCode;
+  use hyper::header::{ContentType, Accept, qitem};
+  use hyper::mime::{Mime, TopLevel, SubLevel};
+
+  #[test]
+  fn http_status_maps_each_kind() {
+    assert_eq!(ErrorKind::BadRequest.http_status(), 400);
+    assert_eq!(ErrorKind::NotFound.http_status(), 404);
+    assert_eq!(ErrorKind::ServerError.http_status(), 500);
+  }
+
+  #[test]
+  fn is_inspectors_match_their_constructor() {
+    assert!(Error::bad_request("x").is_bad_request());
+    assert!(!Error::bad_request("x").is_not_found());
+    assert!(Error::not_found("x").is_not_found());
+    assert!(Error::server_error("x").is_server_error());
+  }
+
+  #[test]
+  fn into_reply_sets_status_from_kind() {
+    let resp = Error::not_found("missing").into_reply().to_http();
+    assert_eq!(resp.status(), StatusCode::NotFound);
+  }
+
+  #[test]
+  fn into_reply_negotiates_content_type_from_attached_req() {
+    let msgpack = Mime(TopLevel::Application, SubLevel::Ext("msgpack".to_string()), vec![]);
+    let req = Req::new("widgets".to_string(), Method::Get, None, Some(Accept(vec![qitem(msgpack.clone())])));
+    let resp = Error::server_error("boom").with_req(req).into_reply().to_http();
+    assert_eq!(resp.headers().get::<ContentType>(), Some(&ContentType(msgpack)));
+  }
+
+  #[test]
+  fn into_reply_defaults_to_json_without_a_req() {
+    let resp = Error::bad_request("boom").into_reply().to_http();
+    let json = Mime(TopLevel::Application, SubLevel::Json, vec![]);
+    assert_eq!(resp.headers().get::<ContentType>(), Some(&ContentType(json)));
+  }
+}
@@ -0,0 +1,134 @@
+use {Request, Error, JsonValue};
+use serde::de::DeserializeOwned;
+use serde_json;
+use futures::{BoxFuture, Future};
+use futures::future::err;
+
+/**
+Something that can be pulled out of a `Request`, à la jsonrpc-v2/actix-web's `Params<T>`.
+
+Implement this for whatever you want handlers to receive instead of the raw `Request` —
+`handler_fn` below uses it to let a plain `Fn(Json<Body>, Query<Filter>) -> BoxFuture<...>`
+closure register directly as an action, with extraction (and its failure mode) handled once
+here rather than repeated in every handler.
+*/
+pub trait FromRequest: Sized {
+  fn from_request(req: &Request) -> Result<Self, Error>;
+}
+
+/// Deserializes the request body (`req.data()`) as `T`.
+pub struct Json<T>(pub T);
+
+/// Deserializes the request's query params (`req.params()`) as `T`.
+pub struct Query<T>(pub T);
+
+/// The request's `id`, or a bad-request error if one wasn't supplied.
+pub struct Id(pub String);
+
+impl<T: DeserializeOwned> FromRequest for Json<T> {
+  fn from_request(req: &Request) -> Result<Json<T>, Error> {
+    serde_json::from_value(req.data().clone())
+      .map(Json)
+      .map_err(|e| Error::bad_request(json!({"error": format!("{}", e)})).with_req(req.to_req()))
+  }
+}
+
+impl<T: DeserializeOwned> FromRequest for Query<T> {
+  fn from_request(req: &Request) -> Result<Query<T>, Error> {
+    let params = JsonValue::Object(req.params().clone());
+    serde_json::from_value(params)
+      .map(Query)
+      .map_err(|e| Error::bad_request(json!({"error": format!("{}", e)})).with_req(req.to_req()))
+  }
+}
+
+impl FromRequest for Id {
+  fn from_request(req: &Request) -> Result<Id, Error> {
+    req.id().clone()
+      .map(Id)
+      .ok_or_else(|| Error::bad_request(json!({"error": "missing id in request"})).with_req(req.to_req()))
+  }
+}
+
+/// Implemented for closures of one or two arguments that are all `FromRequest` extractors,
+/// so `handler_fn` can build those arguments from a `&Request`.
+pub trait Handler<Args>: Send + Sync {
+  fn call(&self, req: &Request) -> BoxFuture<JsonValue, Error>;
+}
+
+impl<F, A> Handler<(A,)> for F
+  where F: Fn(A) -> BoxFuture<JsonValue, Error> + Send + Sync, A: FromRequest
+{
+  fn call(&self, req: &Request) -> BoxFuture<JsonValue, Error> {
+    match A::from_request(req) {
+      Ok(a) => (self)(a),
+      Err(e) => err(e).boxed(),
+    }
+  }
+}
+
+impl<F, A, B> Handler<(A, B)> for F
+  where F: Fn(A, B) -> BoxFuture<JsonValue, Error> + Send + Sync, A: FromRequest, B: FromRequest
+{
+  fn call(&self, req: &Request) -> BoxFuture<JsonValue, Error> {
+    match (A::from_request(req), B::from_request(req)) {
+      (Ok(a), Ok(b)) => (self)(a, b),
+      (Err(e), _) => err(e).boxed(),
+      (_, Err(e)) => err(e).boxed(),
+    }
+  }
+}
+
+/// Wraps a closure over `FromRequest` extractors into the `Fn(&Request) -> BoxFuture<...>`
+/// shape that `Resource::action` expects, running the extraction (and
+/// mapping any failure to a `BadRequest`) before calling through to the closure.
+pub fn handler_fn<F, Args>(handler: F) -> impl Fn(&Request) -> BoxFuture<JsonValue, Error> + Send + Sync
+  where F: Handler<Args> + 'static, Args: 'static
+{
+  move |req: &Request| handler.call(req)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use {JsonObject, Method};
+
+  fn make_req(data: JsonValue, params: JsonObject, id: Option<&str>) -> Request {
+    Request::new("widgets".to_string(), Method::Get, id.map(|s| s.to_string()), data, params)
+  }
+
+  #[test]
+  fn json_deserializes_the_request_body() {
+    let req = make_req(JsonValue::from(42u64), JsonObject::new(), None);
+    let Json(val) = Json::<u64>::from_request(&req).unwrap();
+    assert_eq!(val, 42);
+  }
+
+  #[test]
+  fn json_deserialization_failure_is_a_bad_request() {
+    let req = make_req(JsonValue::String("nope".to_string()), JsonObject::new(), None);
+    assert!(Json::<u64>::from_request(&req).unwrap_err().is_bad_request());
+  }
+
+  #[test]
+  fn query_deserializes_the_request_params() {
+    let mut params = JsonObject::new();
+    params.insert("limit".to_string(), JsonValue::from(10u64));
+    let req = make_req(JsonValue::Null, params.clone(), None);
+    let Query(val) = Query::<JsonObject>::from_request(&req).unwrap();
+    assert_eq!(val, params);
+  }
+
+  #[test]
+  fn id_extracts_the_request_id() {
+    let req = make_req(JsonValue::Null, JsonObject::new(), Some("42"));
+    let Id(id) = Id::from_request(&req).unwrap();
+    assert_eq!(id, "42");
+  }
+
+  #[test]
+  fn id_missing_is_a_bad_request() {
+    let req = make_req(JsonValue::Null, JsonObject::new(), None);
+    assert!(Id::from_request(&req).unwrap_err().is_bad_request());
+  }
+}
@@ -1,20 +1,36 @@
 extern crate ws;
 extern crate futures;
 extern crate tokio_core;
+extern crate tokio_timer;
+extern crate serde;
 extern crate serde_json;
+extern crate rmp_serde;
 
 use serde_json::Value as JsonValue;
 
 pub type Params = serde_json::value::Map<String, JsonValue>;
+pub type JsonObject = serde_json::value::Map<String, JsonValue>;
+
+mod error;
+pub use error::{Error, ErrorKind};
 
 mod req;
 pub use req::{Req, Method};
 
+mod request;
+pub use request::Request;
+
 mod server;
 pub use server::Server;
 
 mod reply;
-pub use reply::Reply;
+pub use reply::{Reply, KeepaliveConfig};
+
+mod sender;
+pub use sender::Sender;
+
+mod serializer;
+pub use serializer::{Serializer, SerializerRegistry, JsonSerializer, MsgpackSerializer};
 
 mod adapter;
 pub use adapter::{Adapter, MemoryAdapter}; // TODO memory adapter should probably eventually go in its own crate
@@ -22,6 +38,9 @@ pub use adapter::{Adapter, MemoryAdapter}; // TODO memory adapter should probabl
 mod resource;
 pub use resource::{Resource}; // TODO memory adapter should probably eventually go in its own crate
 
+mod extract;
+pub use extract::{FromRequest, Json, Query, Id, Handler, handler_fn};
+
 #[cfg(test)]
 mod tests {
   use super::*;
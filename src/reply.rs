@@ -1,25 +1,69 @@
 use super::{JsonValue, Req};
 use hyper::server as http;
 use hyper::Error as HyperError;
-use hyper::header::{ContentLength, ContentType, Accept};
+use hyper::header::{ContentLength, ContentType};
 use hyper::mime::{Mime, TopLevel, SubLevel};
 use hyper;
 use hyper::Chunk as HyperChunk;
 use futures::{Poll, Stream, Async};
 use futures::sync::mpsc;
+use tokio_timer::{Timer, Interval};
+use std::time::{Duration, Instant};
 use Sender;
+use serializer::SerializerRegistry;
 
 type MpscReceiver = mpsc::UnboundedReceiver<HyperChunk>;
 
+/**
+Tuning for the SSE heartbeat/inactivity checks on a streamed `Reply`.
+
+Nothing else keeps a `text/event-stream` connection honest: proxies drop it once it goes
+quiet, and a client that vanished without closing the socket would otherwise hang around
+forever. `KeepaliveConfig` is the knob for both problems: the stream checks in every
+`ping_interval`, and a check with no real data sent since the previous one counts as a missed
+tick. If that makes `max_failures` consecutive missed ticks, or `inactive_limit` has passed
+since the last real data, the stream closes; otherwise a no-op SSE comment (`":\n\n"`) goes
+out to keep proxies happy. Only data sent through the `Sender` counts as activity; nothing the
+client sends back is observed.
+*/
+#[derive(Debug, Clone)]
+pub struct KeepaliveConfig {
+  pub ping_interval: Duration,
+  pub inactive_limit: Duration,
+  pub max_failures: u32,
+}
+
+impl Default for KeepaliveConfig {
+  fn default() -> KeepaliveConfig {
+    KeepaliveConfig {
+      ping_interval: Duration::from_secs(30),
+      inactive_limit: Duration::from_secs(40),
+      max_failures: 3,
+    }
+  }
+}
+
+fn status_from_code(code: i64) -> hyper::StatusCode {
+  match code {
+    200 => hyper::StatusCode::Ok,
+    201 => hyper::StatusCode::Created,
+    400 => hyper::StatusCode::BadRequest,
+    404 => hyper::StatusCode::NotFound,
+    406 => hyper::StatusCode::NotAcceptable,
+    500 => hyper::StatusCode::InternalServerError,
+    other => hyper::StatusCode::Unregistered(other as u16),
+  }
+}
+
 pub struct Reply {
   data: ReplyData,
-  code: i64, // TODO replace with enum of errors, etc
+  code: i64,
   req: Option<Req>,
 }
 
 enum ReplyData {
   Value(JsonValue),
-  Stream(MpscReceiver),
+  Stream(Keepalive),
 }
 
 impl Reply {
@@ -32,15 +76,45 @@ impl Reply {
     }
   }
 
+  /// Renders the reply using the default `SerializerRegistry` (JSON, with MessagePack
+  /// available via `Accept: application/msgpack`).
   pub fn to_http(self) -> http::Response<Body> {
-    let resp = http::Response::new();
+    self.to_http_with_serializers(&SerializerRegistry::default())
+  }
+
+  /// Renders the reply, picking a `Serializer` from `serializers` based on the stored
+  /// request's `Accept` header. Falls back to the registry's default serializer when there
+  /// is no `Accept` header, and responds `406 Not Acceptable` when there's an `Accept`
+  /// header but nothing registered can satisfy it.
+  pub fn to_http_with_serializers(self, serializers: &SerializerRegistry) -> http::Response<Body> {
+    let resp = http::Response::new().with_status(status_from_code(self.code));
 
     match self.data {
       ReplyData::Value(val) => {
-        let resp_str = val.to_string();
-        resp
-          .with_header(ContentLength(resp_str.len() as u64))
-          .with_body(Body::Once(Some(resp_str.into())))
+        let accept = self.req.as_ref().and_then(|req| req.accept().clone());
+        let serializer = match accept {
+          Some(ref accept) => match serializers.find(accept) {
+            Some(serializer) => serializer,
+            None => return resp
+              .with_status(hyper::StatusCode::NotAcceptable)
+              .with_body(Body::Once(None)),
+          },
+          None => match serializers.default_serializer() {
+            Some(serializer) => serializer,
+            None => return resp
+              .with_status(hyper::StatusCode::InternalServerError)
+              .with_body(Body::Once(None)),
+          },
+        };
+        match serializer.serialize(&val) {
+          Ok(bytes) => resp
+            .with_header(ContentType(serializer.content_type()))
+            .with_header(ContentLength(bytes.len() as u64))
+            .with_body(Body::Once(Some(bytes.into()))),
+          Err(_) => resp
+            .with_status(hyper::StatusCode::InternalServerError)
+            .with_body(Body::Once(None)),
+        }
       },
       ReplyData::Stream(stream) => {
         resp
@@ -50,22 +124,101 @@ impl Reply {
     }
   }
 
+  /// Starts a streamed (`text/event-stream`) reply, keeping it alive with the default
+  /// `KeepaliveConfig` (checks every 30s; a check with no data since the previous one closes
+  /// the stream once 40s have passed since the last data or 3 consecutive checks found none,
+  /// and sends a ping otherwise).
   pub fn new_streamed(code: i64, req: Option<Req>) -> (Sender, Reply) {
+    Reply::new_streamed_with_keepalive(code, req, KeepaliveConfig::default())
+  }
+
+  /// Starts a streamed reply with a custom `KeepaliveConfig`.
+  pub fn new_streamed_with_keepalive(code: i64, req: Option<Req>, keepalive: KeepaliveConfig) -> (Sender, Reply) {
     let (tx, rx) = mpsc::unbounded();
     let reply = Reply {
       code: code,
       req: req,
-      data: ReplyData::Stream(rx)
+      data: ReplyData::Stream(Keepalive::new(rx, keepalive)),
     };
     let sender = Sender::new(tx);
     (sender, reply)
   }
 }
 
+/// Wraps a streamed reply's receiver with ping/inactivity tracking, so that every consumer
+/// of a `Body::Stream` gets keepalive behavior for free instead of reimplementing it.
+struct Keepalive {
+  inner: MpscReceiver,
+  ticks: Interval,
+  max_failures: u32,
+  inactive_limit: Duration,
+  missed_ticks: u32,
+  data_since_tick: bool,
+  last_activity: Instant,
+}
+
+impl Keepalive {
+  fn new(inner: MpscReceiver, config: KeepaliveConfig) -> Keepalive {
+    Keepalive {
+      inner: inner,
+      ticks: Timer::default().interval(config.ping_interval),
+      max_failures: config.max_failures,
+      inactive_limit: config.inactive_limit,
+      missed_ticks: 0,
+      data_since_tick: false,
+      last_activity: Instant::now(),
+    }
+  }
+
+  // `max_failures` and `inactive_limit` are both independently honored: whichever one a
+  // caller set tighter is the one that actually closes the connection first.
+  fn should_close(&self) -> bool {
+    self.missed_ticks >= self.max_failures || self.last_activity.elapsed() >= self.inactive_limit
+  }
+}
+
+impl Stream for Keepalive {
+  type Item = HyperChunk;
+  type Error = ();
+
+  fn poll(&mut self) -> Poll<Option<HyperChunk>, ()> {
+    match try!(self.inner.poll()) {
+      Async::Ready(Some(chunk)) => {
+        self.missed_ticks = 0;
+        self.data_since_tick = true;
+        self.last_activity = Instant::now();
+        return Ok(Async::Ready(Some(chunk)));
+      },
+      Async::Ready(None) => return Ok(Async::Ready(None)), // sender(s) dropped, stream is done
+      Async::NotReady => {},
+    }
+
+    match self.ticks.poll() {
+      Ok(Async::Ready(Some(_))) => {
+        if self.data_since_tick {
+          // real data already kept the connection alive this interval; don't double up
+          self.data_since_tick = false;
+          self.poll() // poll again so the interval registers a wakeup for its next tick
+        } else {
+          self.missed_ticks += 1;
+          if self.should_close() {
+            Ok(Async::Ready(None)) // max_failures or inactive_limit reached, give up on the client
+          } else {
+            Ok(Async::Ready(Some(b":\n\n"[..].into()))) // SSE comment, a no-op heartbeat
+          }
+        }
+      },
+      Ok(Async::Ready(None)) => Ok(Async::Ready(None)),
+      Ok(Async::NotReady) => Ok(Async::NotReady),
+      Err(_) => Err(()),
+    }
+  }
+}
+
 /// A `Stream` for `HyperChunk`s used in requests and responses.
 pub enum Body {
   Once(Option<HyperChunk>),
-  Stream(MpscReceiver),
+  Stream(Keepalive),
 }
 
 impl Stream for Body {
@@ -84,3 +237,82 @@ impl Stream for Body {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use futures::sync::mpsc;
+
+  fn heartbeat() -> HyperChunk {
+    b":\n\n"[..].into()
+  }
+
+  #[test]
+  fn keepalive_pings_then_closes_after_max_failures() {
+    let (_tx, rx) = mpsc::unbounded();
+    let config = KeepaliveConfig {
+      ping_interval: Duration::from_millis(15),
+      inactive_limit: Duration::from_secs(1000), // high enough to never be the trigger here
+      max_failures: 2,
+    };
+    let mut stream = Keepalive::new(rx, config).wait();
+    assert_eq!(stream.next().unwrap().unwrap(), heartbeat()); // 1st missed tick: still under max_failures
+    assert!(stream.next().is_none()); // 2nd missed tick: max_failures reached, close
+  }
+
+  #[test]
+  fn keepalive_closes_on_inactive_limit_even_with_high_max_failures() {
+    let (_tx, rx) = mpsc::unbounded();
+    let config = KeepaliveConfig {
+      ping_interval: Duration::from_millis(20),
+      inactive_limit: Duration::from_millis(30),
+      max_failures: 1000, // never the binding constraint here
+    };
+    let mut stream = Keepalive::new(rx, config).wait();
+    assert_eq!(stream.next().unwrap().unwrap(), heartbeat()); // ~20ms elapsed, under inactive_limit
+    assert!(stream.next().is_none()); // ~40ms elapsed, past inactive_limit, close
+  }
+
+  #[test]
+  fn keepalive_honors_custom_max_failures_regardless_of_inactive_limit() {
+    // a max_failures bigger than inactive_limit/ping_interval implies must not be clamped down
+    let (_tx, rx) = mpsc::unbounded();
+    let config = KeepaliveConfig {
+      ping_interval: Duration::from_millis(10),
+      inactive_limit: Duration::from_millis(15), // would imply ~2 ticks if max_failures were derived from it
+      max_failures: 5,
+    };
+    let keepalive = Keepalive::new(rx, config);
+    assert_eq!(keepalive.max_failures, 5);
+  }
+
+  #[test]
+  fn keepalive_suppresses_heartbeat_for_the_interval_real_data_arrives_in() {
+    let (tx, rx) = mpsc::unbounded();
+    let config = KeepaliveConfig {
+      ping_interval: Duration::from_millis(20),
+      inactive_limit: Duration::from_secs(1000),
+      max_failures: 1000,
+    };
+    let chunk: HyperChunk = b"event: hi\n\n"[..].into();
+    tx.unbounded_send(chunk.clone()).unwrap();
+    let mut stream = Keepalive::new(rx, config).wait();
+    assert_eq!(stream.next().unwrap().unwrap(), chunk); // real data delivered immediately
+    // the tick that lands in the same interval as the chunk is suppressed, so the next
+    // item out of the stream is the *following* tick's heartbeat, not an immediate one
+    assert_eq!(stream.next().unwrap().unwrap(), heartbeat());
+  }
+
+  #[test]
+  fn keepalive_closes_when_senders_are_dropped() {
+    let (tx, rx) = mpsc::unbounded();
+    let config = KeepaliveConfig {
+      ping_interval: Duration::from_secs(1000),
+      inactive_limit: Duration::from_secs(1000),
+      max_failures: 1000,
+    };
+    drop(tx);
+    let mut stream = Keepalive::new(rx, config).wait();
+    assert!(stream.next().is_none());
+  }
+}
@@ -0,0 +1,57 @@
+use hyper::header::Accept;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Method {
+  // indempotent methods (must be able to call many times and it'll have the same effect/return value as just once)
+  List, // -> GET /resource
+  Get, // -> GET /resource/123
+  Delete, // -> DELETE /resource/123
+  // not indempotent
+  Post, // -> POST /resource
+  Patch, // -> PATCH /resource/123
+  Listen, // -> GET /resource or (maybe?) GET /resource/123 with content-type text/event-stream
+  Action(String), // -> POST /resource/123/actionname
+}
+
+/**
+A lightweight snapshot of the request that produced a `Reply`.
+
+`Reply` keeps one of these around instead of the full request so it can answer questions
+like "what method/resource was this for?" while building the HTTP response, without having
+to hold onto the request body or params for the lifetime of the reply.
+*/
+#[derive(Debug, Clone)]
+pub struct Req {
+  resource: String,
+  method: Method,
+  id: Option<String>,
+  accept: Option<Accept>,
+}
+
+impl Req {
+  pub fn new(resource: String, method: Method, id: Option<String>, accept: Option<Accept>) -> Req {
+    Req {
+      resource: resource,
+      method: method,
+      id: id,
+      accept: accept,
+    }
+  }
+
+  pub fn resource(&self) -> &str {
+    &self.resource
+  }
+
+  pub fn method(&self) -> &Method {
+    &self.method
+  }
+
+  pub fn id(&self) -> &Option<String> {
+    &self.id
+  }
+
+  /// The request's `Accept` header, if any, used by `Reply::to_http` for content negotiation.
+  pub fn accept(&self) -> &Option<Accept> {
+    &self.accept
+  }
+}
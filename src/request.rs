@@ -1,18 +1,5 @@
-use super::{Params, JsonValue, Reply};
-use reply::make_reply;
-
-#[derive(Debug, Clone)]
-pub enum Method {
-  // indempotent methods (must be able to call many times and it'll have the same effect/return value as just once)
-  List, // -> GET /resource
-  Get, // -> GET /resource/123
-  Delete, // -> DELETE /resource/123
-  // not indempotent
-  Post, // -> POST /resource
-  Patch, // -> PATCH /resource/123
-  Listen, // -> GET /resource or (maybe?) GET /resource/123 with content-type text/event-stream
-  Action(String), // -> POST /resource/123/actionname
-}
+use super::{Params, JsonValue, Reply, Req, Method};
+use hyper::header::Accept;
 
 #[derive(Debug)]
 pub struct Request {
@@ -21,6 +8,7 @@ pub struct Request {
   data: JsonValue,
   resource: String,
   method: Method,
+  accept: Option<Accept>,
 }
 
 impl Request {
@@ -30,12 +18,31 @@ impl Request {
       method: method,
       id: id,
       data: data,
-      params: params
+      params: params,
+      accept: None,
     }
   }
 
+  /// Attaches the request's `Accept` header, later threaded into `Reply` for content
+  /// negotiation in `Reply::to_http`.
+  pub fn with_accept(mut self, accept: Option<Accept>) -> Request {
+    self.accept = accept;
+    self
+  }
+
+  /// A lightweight snapshot of this request, for stashing in a `Reply` or `Error` so the
+  /// originating `Accept` header is still around for content negotiation later.
+  pub fn to_req(&self) -> Req {
+    Req::new(self.resource.clone(), self.method.clone(), self.id.clone(), self.accept.clone())
+  }
+
   pub fn into_reply(self, reply: JsonValue) -> Reply {
-    make_reply(self, reply)
+    let code = match self.method {
+      Method::Post => 201,
+      _ => 200,
+    };
+    let req = self.to_req();
+    Reply::new(code, Some(req), reply)
   }
 
   pub fn method(&self) -> &Method {
@@ -61,4 +68,4 @@ impl Request {
   pub fn data_mut(&mut self) -> &mut JsonValue {
     &mut self.data
   }
-}
\ No newline at end of file
+}
@@ -0,0 +1,237 @@
+use {Adapter, Request, Reply, Method, Error, JsonValue};
+use futures::{BoxFuture, Future};
+use futures::future::{ok, err};
+use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
+
+type BeforeHook = Arc<Fn(Request) -> BoxFuture<Request, Error> + Send + Sync>;
+type AfterHook = Arc<Fn(Reply) -> BoxFuture<Reply, Error> + Send + Sync>;
+type ActionHook = Arc<Fn(&Request) -> BoxFuture<JsonValue, Error> + Send + Sync>;
+
+/// Which requests a hook registered with `Resource::before`/`Resource::after` applies to.
+#[derive(Clone)]
+pub enum MethodFilter {
+  All,
+  Only(Vec<Method>),
+}
+
+impl MethodFilter {
+  fn matches(&self, method: &Method) -> bool {
+    match *self {
+      MethodFilter::All => true,
+      MethodFilter::Only(ref methods) => methods.contains(method),
+    }
+  }
+}
+
+/**
+Mounts an `Adapter` and dispatches `Request`s to it, with `before`/`after` hooks run around
+every call.
+
+A `Resource` is the thing you actually hand to `Server::mount`. On its own it just forwards
+requests to its `Adapter`, but a `before` hook gets first look at the request — mutate it,
+reject it outright with an `Err` — and an `after` hook gets last look at the reply before it
+goes out. Most of what people reach for a middleware stack for (checking a token, trimming
+a field off a response, writing an access log line) fits naturally into one or the other,
+without the `Adapter` itself needing to know any of it happened.
+*/
+pub struct Resource {
+  adapter: Arc<Adapter>,
+  before_hooks: Vec<(MethodFilter, BeforeHook)>,
+  after_hooks: Vec<(MethodFilter, AfterHook)>,
+  actions: Arc<Mutex<HashMap<String, ActionHook>>>,
+}
+
+impl Resource {
+  pub fn new<A: Adapter + 'static>(adapter: A) -> Resource {
+    Resource {
+      adapter: Arc::new(adapter),
+      before_hooks: Vec::new(),
+      after_hooks: Vec::new(),
+      actions: Arc::new(Mutex::new(HashMap::new())),
+    }
+  }
+
+  /// Registers a hook to run before the adapter is called, for requests matching `methods`.
+  /// The hook can mutate the request (via `data_mut`/etc) or short-circuit the whole
+  /// dispatch by returning `Err`.
+  pub fn before<F>(&mut self, methods: MethodFilter, hook: F) -> &mut Resource
+    where F: Fn(Request) -> BoxFuture<Request, Error> + Send + Sync + 'static
+  {
+    self.before_hooks.push((methods, Arc::new(hook)));
+    self
+  }
+
+  /// Registers a hook to run on the reply after the adapter (and any action handler)
+  /// returns, for requests matching `methods`.
+  pub fn after<F>(&mut self, methods: MethodFilter, hook: F) -> &mut Resource
+    where F: Fn(Reply) -> BoxFuture<Reply, Error> + Send + Sync + 'static
+  {
+    self.after_hooks.push((methods, Arc::new(hook)));
+    self
+  }
+
+  /// Registers a custom RPC-style action, reachable as `Method::Action(name)` (e.g.
+  /// `POST /resource/:id/publish`). The handler gets the full `Request` (so it can read
+  /// `id`/`data`/`params`) and returns the `JsonValue` to reply with.
+  pub fn action<F>(&mut self, name: &str, hook: F) -> &mut Resource
+    where F: Fn(&Request) -> BoxFuture<JsonValue, Error> + Send + Sync + 'static
+  {
+    self.actions.lock().unwrap().insert(name.to_string(), Arc::new(hook));
+    self
+  }
+
+  /// Runs the before-hooks, the adapter (or a registered action handler), then the
+  /// after-hooks for `req`, as a single chained future.
+  pub fn handle(&self, req: Request) -> BoxFuture<Reply, Error> {
+    let method = req.method().clone();
+    let adapter = self.adapter.clone();
+    let actions = self.actions.clone();
+
+    let before: Vec<BeforeHook> = self.before_hooks.iter()
+      .filter(|hook| hook.0.matches(&method))
+      .map(|hook| hook.1.clone())
+      .collect();
+
+    let after: Vec<AfterHook> = self.after_hooks.iter()
+      .filter(|hook| hook.0.matches(&method))
+      .map(|hook| hook.1.clone())
+      .collect();
+
+    before.into_iter()
+      .fold(ok(req).boxed(), |chain, hook| chain.and_then(move |req| hook(req)).boxed())
+      .and_then(move |req| -> BoxFuture<Reply, Error> {
+        let action_name = match *req.method() {
+          Method::Action(ref name) => Some(name.clone()),
+          _ => None,
+        };
+        match action_name {
+          Some(name) => {
+            let hook = actions.lock().unwrap().get(&name).cloned();
+            match hook {
+              Some(hook) => hook(&req).map(move |val| req.into_reply(val)).boxed(),
+              None => {
+                let req_snapshot = req.to_req();
+                err(Error::not_found(format!("no such action: {}", name)).with_req(req_snapshot)).boxed()
+              },
+            }
+          },
+          None => adapter.handle(req),
+        }
+      })
+      .and_then(move |reply| {
+        after.into_iter().fold(ok(reply).boxed(), |chain, hook| chain.and_then(move |reply| hook(reply)).boxed())
+      })
+      .boxed()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use {JsonObject, ErrorKind};
+  use futures::future::ok;
+
+  struct TestAdapter;
+
+  impl Adapter for TestAdapter {
+    fn list(&self, _params: &JsonObject) -> BoxFuture<JsonObject, (ErrorKind, JsonValue)> {
+      ok(JsonObject::new()).boxed()
+    }
+    fn get(&self, _id: &str, _params: &JsonObject) -> BoxFuture<JsonObject, (ErrorKind, JsonValue)> {
+      ok(JsonObject::new()).boxed()
+    }
+    fn post(&self, _data: &JsonObject, _params: &JsonObject) -> BoxFuture<JsonObject, (ErrorKind, JsonValue)> {
+      ok(JsonObject::new()).boxed()
+    }
+    fn patch(&self, _id: &str, _data: &JsonObject, _params: &JsonObject) -> BoxFuture<JsonObject, (ErrorKind, JsonValue)> {
+      ok(JsonObject::new()).boxed()
+    }
+    fn delete(&self, _id: &str, _params: &JsonObject) -> BoxFuture<JsonObject, (ErrorKind, JsonValue)> {
+      ok(JsonObject::new()).boxed()
+    }
+  }
+
+  fn make_req(method: Method) -> Request {
+    Request::new("widgets".to_string(), method, None, JsonValue::Null, JsonObject::new())
+  }
+
+  #[test]
+  fn before_and_after_hooks_run_around_the_adapter_in_registration_order() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut resource = Resource::new(TestAdapter);
+
+    let before_log = log.clone();
+    resource.before(MethodFilter::All, move |req| {
+      before_log.lock().unwrap().push("before".to_string());
+      ok(req).boxed()
+    });
+
+    let after_log_1 = log.clone();
+    resource.after(MethodFilter::All, move |reply| {
+      after_log_1.lock().unwrap().push("after-1".to_string());
+      ok(reply).boxed()
+    });
+    let after_log_2 = log.clone();
+    resource.after(MethodFilter::All, move |reply| {
+      after_log_2.lock().unwrap().push("after-2".to_string());
+      ok(reply).boxed()
+    });
+
+    resource.handle(make_req(Method::List)).wait().unwrap();
+    assert_eq!(*log.lock().unwrap(), vec!["before", "after-1", "after-2"]);
+  }
+
+  #[test]
+  fn before_hook_err_short_circuits_the_adapter_and_after_hooks() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut resource = Resource::new(TestAdapter);
+
+    resource.before(MethodFilter::All, |_req| {
+      err(Error::bad_request("nope")).boxed()
+    });
+    let after_log = log.clone();
+    resource.after(MethodFilter::All, move |reply| {
+      after_log.lock().unwrap().push("after".to_string());
+      ok(reply).boxed()
+    });
+
+    let result = resource.handle(make_req(Method::List)).wait();
+    assert!(result.unwrap_err().is_bad_request());
+    assert!(log.lock().unwrap().is_empty());
+  }
+
+  #[test]
+  fn method_filter_only_excludes_non_matching_methods() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut resource = Resource::new(TestAdapter);
+
+    let before_log = log.clone();
+    resource.before(MethodFilter::Only(vec![Method::Post]), move |req| {
+      before_log.lock().unwrap().push("before".to_string());
+      ok(req).boxed()
+    });
+
+    resource.handle(make_req(Method::List)).wait().unwrap();
+    assert!(log.lock().unwrap().is_empty());
+
+    resource.handle(make_req(Method::Post)).wait().unwrap();
+    assert_eq!(*log.lock().unwrap(), vec!["before"]);
+  }
+
+  #[test]
+  fn action_dispatches_to_the_registered_handler_instead_of_the_adapter() {
+    let mut resource = Resource::new(TestAdapter);
+    resource.action("publish", |_req| ok(json!({"published": true})).boxed());
+
+    let reply = resource.handle(make_req(Method::Action("publish".to_string()))).wait().unwrap();
+    assert_eq!(reply.to_http().status(), ::hyper::StatusCode::Ok);
+  }
+
+  #[test]
+  fn action_with_an_unregistered_name_is_a_not_found_error() {
+    let resource = Resource::new(TestAdapter);
+    let result = resource.handle(make_req(Method::Action("nonexistent".to_string()))).wait();
+    assert!(result.unwrap_err().is_not_found());
+  }
+}
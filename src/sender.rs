@@ -0,0 +1,30 @@
+use futures::sync::mpsc::UnboundedSender;
+use hyper::Chunk as HyperChunk;
+
+/**
+A handle for pushing chunks into a streamed `Reply`.
+
+Returned alongside the `Reply` by `Reply::new_streamed`, a `Sender` is how the rest of the
+app feeds data (e.g. `Method::Listen` events) into an open `text/event-stream` connection.
+Dropping every `Sender` for a stream closes it.
+*/
+pub struct Sender {
+  tx: UnboundedSender<HyperChunk>,
+}
+
+impl Sender {
+  pub fn new(tx: UnboundedSender<HyperChunk>) -> Sender {
+    Sender { tx: tx }
+  }
+
+  /// Pushes a chunk of data to the client. Returns `Err` if the reply has already closed.
+  pub fn send<T: Into<HyperChunk>>(&self, chunk: T) -> Result<(), ()> {
+    self.tx.unbounded_send(chunk.into()).map_err(|_| ())
+  }
+}
+
+impl Clone for Sender {
+  fn clone(&self) -> Sender {
+    Sender { tx: self.tx.clone() }
+  }
+}
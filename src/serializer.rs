@@ -0,0 +1,167 @@
+use super::{JsonValue, Error};
+use hyper::mime::{Mime, TopLevel, SubLevel};
+use hyper::header::{Accept, QualityItem};
+use serde_json;
+use rmp_serde;
+
+/**
+Turns a `JsonValue` into response bytes for a particular content type.
+
+Backtalk's internal representation of a reply is always a `JsonValue`; a `Serializer` is
+what lets that value leave the process as JSON, MessagePack, or whatever else a client asks
+for via `Accept`. Register one with a `SerializerRegistry` to make it available.
+*/
+pub trait Serializer: Send + Sync {
+  fn content_type(&self) -> Mime;
+  fn serialize(&self, value: &JsonValue) -> Result<Vec<u8>, Error>;
+}
+
+pub struct JsonSerializer;
+
+impl Serializer for JsonSerializer {
+  fn content_type(&self) -> Mime {
+    Mime(TopLevel::Application, SubLevel::Json, vec![])
+  }
+
+  fn serialize(&self, value: &JsonValue) -> Result<Vec<u8>, Error> {
+    serde_json::to_vec(value).map_err(|e| Error::server_error(format!("{}", e)))
+  }
+}
+
+pub struct MsgpackSerializer;
+
+impl Serializer for MsgpackSerializer {
+  fn content_type(&self) -> Mime {
+    Mime(TopLevel::Application, SubLevel::Ext("msgpack".to_string()), vec![])
+  }
+
+  fn serialize(&self, value: &JsonValue) -> Result<Vec<u8>, Error> {
+    rmp_serde::to_vec(value).map_err(|e| Error::server_error(format!("{}", e)))
+  }
+}
+
+fn mime_matches(accepted: &Mime, provided: &Mime) -> bool {
+  let &Mime(ref accepted_top, ref accepted_sub, _) = accepted;
+  let &Mime(ref provided_top, ref provided_sub, _) = provided;
+  (*accepted_top == TopLevel::Star || accepted_top == provided_top) &&
+    (*accepted_sub == SubLevel::Star || accepted_sub == provided_sub)
+}
+
+/**
+A lookup of `Serializer`s by content type, consulted by `Reply::to_http` to turn a reply's
+`Accept` header into an actual serialized body. `SerializerRegistry::default()` comes
+pre-populated with JSON (the default, used when a request has no `Accept` header) and
+MessagePack, while `SerializerRegistry::new()` starts empty; register more with `register`.
+*/
+pub struct SerializerRegistry {
+  serializers: Vec<Box<Serializer>>,
+}
+
+impl SerializerRegistry {
+  pub fn new() -> SerializerRegistry {
+    SerializerRegistry { serializers: Vec::new() }
+  }
+
+  pub fn register<S: Serializer + 'static>(&mut self, serializer: S) {
+    self.serializers.push(Box::new(serializer));
+  }
+
+  /// The serializer used when a request has no `Accept` header at all. `None` if nothing
+  /// has been `register`ed yet (e.g. a bare `SerializerRegistry::new()`).
+  pub fn default_serializer(&self) -> Option<&Serializer> {
+    self.serializers.get(0).map(|s| &**s)
+  }
+
+  /// Finds the registered serializer that best satisfies the given `Accept` header, honoring
+  /// its `q` preference order (highest quality first, including `*/*` and `type/*` wildcards)
+  /// rather than just the order the header listed its media ranges in.
+  pub fn find(&self, accept: &Accept) -> Option<&Serializer> {
+    let mut by_quality: Vec<&QualityItem<Mime>> = accept.iter().collect();
+    by_quality.sort_by(|a, b| b.quality.cmp(&a.quality));
+    for quality_item in by_quality {
+      for serializer in &self.serializers {
+        if mime_matches(&quality_item.item, &serializer.content_type()) {
+          return Some(&**serializer);
+        }
+      }
+    }
+    None
+  }
+}
+
+impl Default for SerializerRegistry {
+  fn default() -> SerializerRegistry {
+    let mut registry = SerializerRegistry::new();
+    registry.register(JsonSerializer);
+    registry.register(MsgpackSerializer);
+    registry
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use hyper::header::{q, qitem};
+
+  fn json_mime() -> Mime {
+    Mime(TopLevel::Application, SubLevel::Json, vec![])
+  }
+
+  fn msgpack_mime() -> Mime {
+    Mime(TopLevel::Application, SubLevel::Ext("msgpack".to_string()), vec![])
+  }
+
+  #[test]
+  fn mime_matches_exact_types_and_wildcards() {
+    assert!(mime_matches(&json_mime(), &json_mime()));
+    assert!(mime_matches(&Mime(TopLevel::Star, SubLevel::Star, vec![]), &json_mime()));
+    assert!(mime_matches(&Mime(TopLevel::Application, SubLevel::Star, vec![]), &json_mime()));
+    assert!(!mime_matches(&msgpack_mime(), &json_mime()));
+  }
+
+  #[test]
+  fn find_picks_the_only_registered_match() {
+    let registry = SerializerRegistry::default();
+    let accept = Accept(vec![qitem(json_mime())]);
+    assert_eq!(registry.find(&accept).unwrap().content_type(), json_mime());
+  }
+
+  #[test]
+  fn find_honors_quality_over_header_order() {
+    let registry = SerializerRegistry::default();
+    // msgpack is listed first, but json has the higher q, so the lower-quality
+    // msgpack match must not win just because it was seen first
+    let accept = Accept(vec![
+      QualityItem::new(msgpack_mime(), q(0.1)),
+      QualityItem::new(json_mime(), q(0.9)),
+    ]);
+    assert_eq!(registry.find(&accept).unwrap().content_type(), json_mime());
+  }
+
+  #[test]
+  fn find_returns_none_when_nothing_registered_satisfies_the_header() {
+    let registry = SerializerRegistry::default();
+    let accept = Accept(vec![qitem(Mime(TopLevel::Text, SubLevel::Html, vec![]))]);
+    assert!(registry.find(&accept).is_none());
+  }
+
+  #[test]
+  fn msgpack_serializer_round_trips_a_json_value() {
+    let value = json!({"hello": "world"});
+    let bytes = MsgpackSerializer.serialize(&value).unwrap();
+    let decoded: JsonValue = rmp_serde::from_slice(&bytes).unwrap();
+    assert_eq!(decoded, value);
+  }
+
+  #[test]
+  fn default_serializer_is_none_on_an_empty_registry() {
+    let registry = SerializerRegistry::new();
+    assert!(registry.default_serializer().is_none());
+  }
+
+  #[test]
+  fn default_serializer_is_the_first_registered_serializer() {
+    let registry = SerializerRegistry::default();
+    assert_eq!(registry.default_serializer().unwrap().content_type(), json_mime());
+  }
+}